@@ -1,5 +1,6 @@
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use clap::Parser;
@@ -17,6 +18,8 @@ enum Mode {
     Create(CreateOpts),
     Open(OpenOpts),
     ToPng(ToPngOpts),
+    FromImage(FromImageOpts),
+    ViewBinary(ViewBinaryOpts),
 }
 
 #[derive(Parser)]
@@ -41,48 +44,230 @@ struct ToPngOpts {
     pub png_filename: PathBuf,
 }
 
+#[derive(Parser)]
+struct FromImageOpts {
+    pub image_filename: PathBuf,
+    pub field_filename: PathBuf,
+
+    /// Luminance values at or below this threshold become palette index 0 (black).
+    #[arg(long, default_value_t = 0x40)]
+    pub low_threshold: u8,
+
+    /// Luminance values at or above this threshold become palette index 1 (white).
+    #[arg(long, default_value_t = 0xC0)]
+    pub high_threshold: u8,
+}
+
+#[derive(Parser)]
+struct ViewBinaryOpts {
+    pub filename: PathBuf,
+
+    /// How many bytes to lay out per row. If omitted, a default is guessed from the
+    /// file's leading magic bytes.
+    #[arg(short = 'w', long, value_parser = parse_nonzero_row_width)]
+    pub row_width: Option<u32>,
+
+    /// Render each byte as eight monochrome pixels (one per bit) instead of as a
+    /// single grayscale intensity pixel.
+    #[arg(long)]
+    pub bit_expanded: bool,
+}
+
+// rejects a row width of 0, which would make `BinaryViewState::rows()` divide by zero
+fn parse_nonzero_row_width(s: &str) -> Result<u32, String> {
+    let width: u32 = s.parse().map_err(|e| format!("{e}"))?;
+    if width == 0 {
+        return Err("row width must be at least 1".to_string());
+    }
+    Ok(width)
+}
+
+
+// an RGB palette entry; kept distinct from sdl2::pixels::Color so it can derive
+// Serialize/Deserialize for storage in the field file
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct PaletteColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+impl PaletteColor {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    pub fn to_sdl_color(self) -> Color {
+        Color::RGB(self.r, self.g, self.b)
+    }
+}
+
+// the palette a freshly created image starts out with: index 0 is black, index 1 is
+// white, mirroring the old Some(false)/Some(true) convention
+const DEFAULT_PALETTE: [PaletteColor; 2] = [
+    PaletteColor::new(0x00, 0x00, 0x00),
+    PaletteColor::new(0xFF, 0xFF, 0xFF),
+];
+
 
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 struct Image {
     pub width: u32,
     pub height: u32,
-    pub pixels: Vec<Option<bool>>,
+    pub pixels: Vec<Option<u8>>,
 }
 impl Image {
     pub fn new(width: u32, height: u32) -> Self {
         let pixel_count: usize = (width * height).try_into().unwrap();
         let pixels = vec![None; pixel_count];
-        Self {
-            width,
-            height,
-            pixels,
+        Self { width, height, pixels }
+    }
+}
+
+
+// how a layer's pixels are combined with whatever is already accumulated from the
+// layers below it; `None` always acts as transparent, regardless of mode, so only
+// doubly-covered pixels ever go through the boolean-style math
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Xor,
+}
+impl BlendMode {
+    pub fn next(self) -> Self {
+        match self {
+            BlendMode::Normal => BlendMode::Multiply,
+            BlendMode::Multiply => BlendMode::Screen,
+            BlendMode::Screen => BlendMode::Xor,
+            BlendMode::Xor => BlendMode::Normal,
         }
     }
 }
 
+// a palette index of 0 reads as "off", anything else as "on"; this lets the
+// boolean-style blend modes work over indexed pixels the same way they would over
+// the old true/false tristate
+#[inline]
+fn index_is_on(index: u8) -> bool {
+    index != 0
+}
 
-struct UiState {
+#[inline]
+fn bool_to_index(on: bool) -> u8 {
+    if on { 1 } else { 0 }
+}
+
+// combines one layer's pixel (`upper`) with whatever has been composited from the
+// layers beneath it so far (`lower`); `None` passes the other value through unchanged
+fn composite_pixel(upper: Option<u8>, lower: Option<u8>, mode: BlendMode) -> Option<u8> {
+    match (upper, lower) {
+        (None, lower) => lower,
+        (upper, None) => upper,
+        (Some(upper_index), Some(lower_index)) => Some(match mode {
+            BlendMode::Normal => upper_index,
+            BlendMode::Multiply => bool_to_index(index_is_on(upper_index) && index_is_on(lower_index)),
+            BlendMode::Screen => bool_to_index(index_is_on(upper_index) || index_is_on(lower_index)),
+            BlendMode::Xor => bool_to_index(index_is_on(upper_index) != index_is_on(lower_index)),
+        }),
+    }
+}
+
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct Layer {
     pub image: Image,
+    pub blend_mode: BlendMode,
+}
+impl Layer {
+    pub fn new(image: Image) -> Self {
+        Self { image, blend_mode: BlendMode::Normal }
+    }
+}
+
+
+// a document is a shared palette plus an ordered stack of layers (bottom-most
+// first); layers are flattened bottom-to-top through their own blend modes to
+// produce the image the user actually sees and exports
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct Document {
+    pub palette: Vec<PaletteColor>,
+    pub active_layer: usize,
+    pub layers: Vec<Layer>,
+}
+impl Document {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            palette: DEFAULT_PALETTE.to_vec(),
+            active_layer: 0,
+            layers: vec![Layer::new(Image::new(width, height))],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.layers[0].image.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.layers[0].image.height
+    }
+}
+
+struct UiState {
+    pub document: Document,
     pub x: u32,
     pub y: u32,
-    pub setting_mode: bool,
+    pub setting_mode: u8,
     pub going_right: bool,
+    pub anchor: Option<(u32, u32)>,
 }
 impl UiState {
-    pub fn new(image: Image) -> Self {
+    pub fn new(document: Document) -> Self {
         Self {
-            image,
+            document,
             x: 0,
             y: 0,
-            setting_mode: false,
+            setting_mode: 0,
             going_right: true,
+            anchor: None,
+        }
+    }
+
+    pub fn active_layer(&self) -> &Layer {
+        &self.document.layers[self.document.active_layer]
+    }
+
+    pub fn active_image_mut(&mut self) -> &mut Image {
+        &mut self.document.layers[self.document.active_layer].image
+    }
+}
+
+
+// moves the cursor to the next pixel in serpentine order: rightward until the row's
+// end, then leftward on the next row, and so on (flips `going_right` at each wrap)
+fn advance_cursor(ui_state: &mut UiState) {
+    let width = ui_state.document.width();
+    let height = ui_state.document.height();
+    if ui_state.going_right {
+        if ui_state.x < width - 1 {
+            ui_state.x += 1;
+        } else {
+            ui_state.going_right = false;
+            if ui_state.y < height - 1 {
+                ui_state.y += 1;
+            }
+        }
+    } else if ui_state.x > 0 {
+        ui_state.x -= 1;
+    } else {
+        ui_state.going_right = true;
+        if ui_state.y < height - 1 {
+            ui_state.y += 1;
         }
     }
 }
 
 
-const COLOR_TRUE: Color = Color::RGB(0xFF, 0xFF, 0xFF);
-const COLOR_FALSE: Color = Color::RGB(0x00, 0x00, 0x00);
 const COLOR_NONE: Color = Color::RGB(0x7F, 0x7F, 0x7F);
 const COLOR_CURSOR: Color = Color::RGB(0xFF, 0x00, 0x00);
 const COLOR_PREVIEW_FRAME: Color = Color::RGB(0x00, 0x00, 0xFF);
@@ -92,46 +277,215 @@ const COLOR_FULL_FRAME: Color = Color::RGB(0x33, 0x33, 0x33);
 macro_rules! u32 { ($val:expr) => (u32::try_from($val).unwrap()); }
 macro_rules! i32 { ($val:expr) => (i32::try_from($val).unwrap()); }
 macro_rules! usize { ($val:expr) => (usize::try_from($val).unwrap()); }
+macro_rules! u8 { ($val:expr) => (u8::try_from($val).unwrap()); }
 
 
+// flattens every layer into a single image, bottom-to-top, applying each layer's
+// own blend mode as it's folded into the accumulated result
+fn composite_document(document: &Document) -> Image {
+    let width = document.width();
+    let height = document.height();
+    let mut pixels = vec![None; usize!(width) * usize!(height)];
+    for layer in &document.layers {
+        for (result_pixel, &layer_pixel) in pixels.iter_mut().zip(layer.image.pixels.iter()) {
+            *result_pixel = composite_pixel(layer_pixel, *result_pixel, layer.blend_mode);
+        }
+    }
+    Image { width, height, pixels }
+}
+
+
+// fills every pixel reachable from (seed_x, seed_y) via orthogonal neighbors that share
+// its current value, using the classic span-based scanline algorithm instead of recursing
+// pixel-by-pixel (which would blow the stack on large contiguous regions)
+fn flood_fill(image: &mut Image, seed_x: u32, seed_y: u32, new_value: Option<u8>) {
+    let width = image.width;
+    let height = image.height;
+    let target_value = image.pixels[usize!(seed_y * width + seed_x)];
+    if target_value == new_value {
+        // filling with the value that's already there would loop forever
+        return;
+    }
+
+    let mut stack = vec![(seed_x, seed_y)];
+    while let Some((x, y)) = stack.pop() {
+        if image.pixels[usize!(y * width + x)] != target_value {
+            // this seed's span has already been filled by an earlier pop
+            continue;
+        }
+
+        let mut left = x;
+        while left > 0 && image.pixels[usize!(y * width + (left - 1))] == target_value {
+            left -= 1;
+        }
+        let mut right = x;
+        while right + 1 < width && image.pixels[usize!(y * width + (right + 1))] == target_value {
+            right += 1;
+        }
+
+        for span_x in left..=right {
+            image.pixels[usize!(y * width + span_x)] = new_value;
+        }
+
+        if y > 0 {
+            seed_flood_row(image, left, right, y - 1, target_value, &mut stack);
+        }
+        if y + 1 < height {
+            seed_flood_row(image, left, right, y + 1, target_value, &mut stack);
+        }
+    }
+}
+
+// scans [left, right] on row_y and pushes one seed coordinate per maximal run of
+// target_value pixels, so the caller doesn't push (and later rescan) every pixel in the run
+fn seed_flood_row(image: &Image, left: u32, right: u32, row_y: u32, target_value: Option<u8>, stack: &mut Vec<(u32, u32)>) {
+    let width = image.width;
+    let mut in_run = false;
+    for x in left..=right {
+        let matches = image.pixels[usize!(row_y * width + x)] == target_value;
+        if matches && !in_run {
+            stack.push((x, row_y));
+        }
+        in_run = matches;
+    }
+}
+
+// sets every pixel in the (inclusive) box between the two given corners
+fn rect_fill(image: &mut Image, x0: u32, y0: u32, x1: u32, y1: u32, value: Option<u8>) {
+    let (min_x, max_x) = if x0 <= x1 { (x0, x1) } else { (x1, x0) };
+    let (min_y, max_y) = if y0 <= y1 { (y0, y1) } else { (y1, y0) };
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            image.pixels[usize!(y * image.width + x)] = value;
+        }
+    }
+}
+
+
+// lays a raw byte buffer out as a navigable pixel grid, either one grayscale pixel
+// per byte or eight monochrome pixels per byte (one per bit)
+struct BinaryViewState {
+    pub buffer: Vec<u8>,
+    pub row_width: u32,
+    pub bit_expanded: bool,
+    pub x: u32,
+    pub y: u32,
+}
+impl BinaryViewState {
+    pub fn new(buffer: Vec<u8>, row_width: u32, bit_expanded: bool) -> Self {
+        Self {
+            buffer,
+            row_width,
+            bit_expanded,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    pub fn columns(&self) -> u32 {
+        if self.bit_expanded { self.row_width * 8 } else { self.row_width }
+    }
+
+    pub fn rows(&self) -> u32 {
+        u32!(self.buffer.len()).div_ceil(self.row_width)
+    }
+
+    // maps a pixel coordinate to the byte it belongs to and, in bit-expanded mode,
+    // the bit within that byte (7 = most significant); in grayscale mode the whole
+    // byte is the pixel, so the "bit" is defined as its least significant one
+    fn byte_and_bit(&self, x: u32, y: u32) -> (usize, u8) {
+        if self.bit_expanded {
+            (usize!(y * self.row_width + x / 8), 7 - u8!(x % 8))
+        } else {
+            (usize!(y * self.row_width + x), 0)
+        }
+    }
+}
+
 #[inline]
-fn color_for_db_bool(value: Option<bool>) -> Color {
-    // I call three-state booleans (true, false, null) "database booleans"
+fn color_for_binary_pixel(state: &BinaryViewState, x: u32, y: u32) -> Color {
+    let (byte_index, bit) = state.byte_and_bit(x, y);
+    let Some(&byte) = state.buffer.get(byte_index) else {
+        return COLOR_NONE;
+    };
+    if state.bit_expanded {
+        if (byte >> bit) & 1 == 1 { Color::RGB(0xFF, 0xFF, 0xFF) } else { Color::RGB(0x00, 0x00, 0x00) }
+    } else {
+        Color::RGB(byte, byte, byte)
+    }
+}
+
+fn toggle_bit_at_cursor(state: &mut BinaryViewState) {
+    let (byte_index, bit) = state.byte_and_bit(state.x, state.y);
+    if let Some(byte) = state.buffer.get_mut(byte_index) {
+        *byte ^= 1 << bit;
+    }
+}
+
+// guesses a sensible row width from a file's leading magic bytes, falling back to a
+// generic default for anything unrecognized
+fn sniff_default_row_width(buffer: &[u8]) -> u32 {
+    if buffer.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        8 // length of the PNG signature
+    } else if buffer.starts_with(b"BM") {
+        14 // size of the BMP BITMAPFILEHEADER
+    } else if buffer.starts_with(b"GIF8") {
+        6 // size of the GIF signature and version
+    } else {
+        16
+    }
+}
+
+
+#[inline]
+fn color_for_pixel(palette: &[PaletteColor], value: Option<u8>) -> Color {
     match value {
-        Some(true) => COLOR_TRUE,
-        Some(false) => COLOR_FALSE,
+        Some(index) => palette.get(usize!(index))
+            .map(|color| color.to_sdl_color())
+            .unwrap_or(COLOR_NONE),
         None => COLOR_NONE,
     }
 }
 
 
-fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
+// the detail-preview/full-image split-pane layout shared by `render` and
+// `render_binary`: a zoomed-in lookaround square around the cursor on the left,
+// and the whole `width`x`height` grid, framed, in the bottom right, with the
+// cursor outlined in both. `color_at` looks up the color to paint at a given
+// coordinate, independently of what's backing the grid (a composited `Image` or
+// a raw byte buffer).
+fn render_split_pane(
+    canvas: &mut Canvas<Window>,
+    width: u32,
+    height: u32,
+    cursor_x: u32,
+    cursor_y: u32,
+    color_at: impl Fn(u32, u32) -> Color,
+) {
     let (canvas_width, canvas_height) = canvas.window().size();
 
     canvas.set_draw_color(COLOR_NONE);
     canvas.clear();
 
-    // paint a detailed preview of the current image
+    // paint a detailed preview around the cursor
     const DETAIL_LOOKAROUND: i32 = 5;
     const DETAIL_PIXEL_SCALE: u32 = 32;
     const DETAIL_BORDER_OFFSET: u32 = 4;
     for y_offset in -DETAIL_LOOKAROUND..=DETAIL_LOOKAROUND {
-        let y_coord = i32!(ui_state.y) + y_offset;
-        if y_coord < 0 || y_coord >= i32!(ui_state.image.height) {
+        let y_coord = i32!(cursor_y) + y_offset;
+        if y_coord < 0 || y_coord >= i32!(height) {
             continue;
         }
         let render_y = i32!(DETAIL_BORDER_OFFSET) + (y_offset + DETAIL_LOOKAROUND) * i32!(DETAIL_PIXEL_SCALE);
 
         for x_offset in -DETAIL_LOOKAROUND..=DETAIL_LOOKAROUND {
-            let x_coord = i32!(ui_state.x) + x_offset;
-            if x_coord < 0 || x_coord >= i32!(ui_state.image.width) {
+            let x_coord = i32!(cursor_x) + x_offset;
+            if x_coord < 0 || x_coord >= i32!(width) {
                 continue;
             }
             let render_x = i32!(DETAIL_BORDER_OFFSET) + (x_offset + DETAIL_LOOKAROUND) * i32!(DETAIL_PIXEL_SCALE);
 
-            let index = usize!(y_coord) * usize!(ui_state.image.width) + usize!(x_coord);
-            let color = color_for_db_bool(ui_state.image.pixels[index]);
-
+            let color = color_at(u32!(x_coord), u32!(y_coord));
             canvas.set_draw_color(color);
             canvas.fill_rect(Rect::new(
                 render_x,
@@ -152,16 +506,15 @@ fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
         DETAIL_PIXEL_SCALE,
     )).unwrap();
 
-    // paint the full image in the bottom right
+    // paint the full grid in the bottom right
     const FULL_IMAGE_PIXEL_SCALE: u32 = 4;
     const FULL_IMAGE_BORDER_OFFSET: u32 = 4;
-    for y in 0..ui_state.image.height {
-        let draw_y = canvas_height - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * (ui_state.image.height - y));
-        for x in 0..ui_state.image.width {
-            let draw_x = canvas_width - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * (ui_state.image.width - x));
-            
-            let i = usize!(y * ui_state.image.width + x);
-            let color = color_for_db_bool(ui_state.image.pixels[i]);
+    for y in 0..height {
+        let draw_y = canvas_height - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * (height - y));
+        for x in 0..width {
+            let draw_x = canvas_width - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * (width - x));
+
+            let color = color_at(x, y);
             canvas.set_draw_color(color);
             canvas.fill_rect(Rect::new(
                 draw_x.try_into().unwrap(),
@@ -173,10 +526,10 @@ fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
     }
 
     // frame it
-    let image_frame_x = canvas_width - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * ui_state.image.width);
-    let image_frame_y = canvas_height - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * ui_state.image.height);
-    let image_frame_width = ui_state.image.width * FULL_IMAGE_PIXEL_SCALE;
-    let image_frame_height = ui_state.image.height * FULL_IMAGE_PIXEL_SCALE;
+    let image_frame_x = canvas_width - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * width);
+    let image_frame_y = canvas_height - (FULL_IMAGE_BORDER_OFFSET + FULL_IMAGE_PIXEL_SCALE * height);
+    let image_frame_width = width * FULL_IMAGE_PIXEL_SCALE;
+    let image_frame_height = height * FULL_IMAGE_PIXEL_SCALE;
     canvas.set_draw_color(COLOR_FULL_FRAME);
     canvas.draw_rect(Rect::new(
         image_frame_x.try_into().unwrap(),
@@ -186,8 +539,8 @@ fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
     )).expect("failed to draw rectangle");
 
     // draw the cursor in the full image
-    let image_cursor_x = i32!(canvas_width) - (i32!(FULL_IMAGE_BORDER_OFFSET) + i32!(FULL_IMAGE_PIXEL_SCALE) * (i32!(ui_state.image.width) - (i32!(ui_state.x) + 1 - DETAIL_LOOKAROUND)));
-    let image_cursor_y = i32!(canvas_height) - (i32!(FULL_IMAGE_BORDER_OFFSET) + i32!(FULL_IMAGE_PIXEL_SCALE) * (i32!(ui_state.image.height) - (i32!(ui_state.y) + 1 - DETAIL_LOOKAROUND)));
+    let image_cursor_x = i32!(canvas_width) - (i32!(FULL_IMAGE_BORDER_OFFSET) + i32!(FULL_IMAGE_PIXEL_SCALE) * (i32!(width) - (i32!(cursor_x) + 1 - DETAIL_LOOKAROUND)));
+    let image_cursor_y = i32!(canvas_height) - (i32!(FULL_IMAGE_BORDER_OFFSET) + i32!(FULL_IMAGE_PIXEL_SCALE) * (i32!(height) - (i32!(cursor_y) + 1 - DETAIL_LOOKAROUND)));
     let image_cursor_size = FULL_IMAGE_PIXEL_SCALE * (2 * u32!(DETAIL_LOOKAROUND) + 1);
     canvas.set_draw_color(COLOR_PREVIEW_FRAME);
     canvas.draw_rect(Rect::new(
@@ -196,14 +549,23 @@ fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
         image_cursor_size,
         image_cursor_size,
     )).expect("failed to draw rectangle");
+}
 
+fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
+    let (canvas_width, canvas_height) = canvas.window().size();
+    let palette = &ui_state.document.palette;
+    let composite = composite_document(&ui_state.document);
+
+    render_split_pane(canvas, composite.width, composite.height, ui_state.x, ui_state.y, |x, y| {
+        color_for_pixel(palette, composite.pixels[usize!(y * composite.width + x)])
+    });
 
     // paint the current color in the top right
     const CURRENT_COLOR_PIXEL_SCALE: u32 = 16;
     const CURRENT_COLOR_BORDER_OFFSET: u32 = 4;
     let current_color_x = canvas_width - (CURRENT_COLOR_BORDER_OFFSET + CURRENT_COLOR_PIXEL_SCALE);
     let current_color_y = CURRENT_COLOR_BORDER_OFFSET;
-    canvas.set_draw_color(if ui_state.setting_mode { COLOR_TRUE } else { COLOR_FALSE });
+    canvas.set_draw_color(color_for_pixel(palette, Some(ui_state.setting_mode)));
     canvas.fill_rect(Rect::new(
         current_color_x.try_into().unwrap(),
         current_color_y.try_into().unwrap(),
@@ -211,6 +573,42 @@ fn render(canvas: &mut Canvas<Window>, ui_state: &UiState) {
         CURRENT_COLOR_PIXEL_SCALE,
     )).expect("failed to draw current color");
 
+    // paint the active layer's blend mode indicator just below the swatch: one
+    // small square per blend mode, the active one lit in the cursor color
+    const BLEND_INDICATOR_SCALE: u32 = 8;
+    const BLEND_INDICATOR_GAP: u32 = 2;
+    let blend_modes = [BlendMode::Normal, BlendMode::Multiply, BlendMode::Screen, BlendMode::Xor];
+    let active_blend_mode = ui_state.active_layer().blend_mode;
+    // right-align the row of squares with the swatch so that all of them stay on the canvas
+    let blend_mode_count = u32!(blend_modes.len());
+    let indicator_row_width = blend_mode_count * BLEND_INDICATOR_SCALE + (blend_mode_count - 1) * BLEND_INDICATOR_GAP;
+    let indicator_row_x = canvas_width - (CURRENT_COLOR_BORDER_OFFSET + indicator_row_width);
+    for (i, &mode) in blend_modes.iter().enumerate() {
+        let indicator_x = indicator_row_x + u32!(i) * (BLEND_INDICATOR_SCALE + BLEND_INDICATOR_GAP);
+        let indicator_y = current_color_y + CURRENT_COLOR_PIXEL_SCALE + BLEND_INDICATOR_GAP;
+        canvas.set_draw_color(if mode == active_blend_mode { COLOR_CURSOR } else { COLOR_FULL_FRAME });
+        canvas.fill_rect(Rect::new(
+            indicator_x.try_into().unwrap(),
+            indicator_y.try_into().unwrap(),
+            BLEND_INDICATOR_SCALE,
+            BLEND_INDICATOR_SCALE,
+        )).expect("failed to draw blend mode indicator");
+    }
+
+    canvas.present();
+}
+
+
+// reuses the detail-preview/full-image split-pane layout of `render`, but walks a
+// raw byte buffer instead of an `Image`
+fn render_binary(canvas: &mut Canvas<Window>, state: &BinaryViewState) {
+    let columns = state.columns();
+    let rows = state.rows();
+
+    render_split_pane(canvas, columns, rows, state.x, state.y, |x, y| {
+        color_for_binary_pixel(state, x, y)
+    });
+
     canvas.present();
 }
 
@@ -232,57 +630,452 @@ fn keycode_to_digit(keycode: Keycode) -> Option<u32> {
 }
 
 
+// decodes an image file (PNG or BMP, picked by extension) into its width, height
+// and a flat row-major buffer of RGB triples
+fn decode_image_rgb(path: &Path) -> (u32, u32, Vec<[u8; 3]>) {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("bmp") => decode_bmp_rgb(path),
+        _ => decode_png_rgb(path),
+    }
+}
+
+fn decode_png_rgb(path: &Path) -> (u32, u32, Vec<[u8; 3]>) {
+    let f = File::open(path)
+        .expect("failed to open source PNG file");
+    let decoder = png::Decoder::new(f);
+    let mut reader = decoder.read_info()
+        .expect("failed to read PNG header");
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)
+        .expect("failed to decode PNG frame");
+    let bytes = &buf[..info.buffer_size()];
+
+    let channel_count = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => panic!("indexed PNGs are not supported by FromImage"),
+    };
+    assert_eq!(info.bit_depth, png::BitDepth::Eight, "only 8-bit PNGs are supported by FromImage");
+
+    let pixel_count = usize!(info.width) * usize!(info.height);
+    let mut pixels = Vec::with_capacity(pixel_count);
+    for chunk in bytes.chunks_exact(channel_count) {
+        let rgb = match channel_count {
+            1 => [chunk[0], chunk[0], chunk[0]],
+            2 => [chunk[0], chunk[0], chunk[0]],
+            3 => [chunk[0], chunk[1], chunk[2]],
+            4 => [chunk[0], chunk[1], chunk[2]],
+            _ => unreachable!(),
+        };
+        pixels.push(rgb);
+    }
+
+    (info.width, info.height, pixels)
+}
+
+fn decode_bmp_rgb(path: &Path) -> (u32, u32, Vec<[u8; 3]>) {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .expect("failed to open source BMP file")
+        .read_to_end(&mut bytes)
+        .expect("failed to read source BMP file");
+
+    assert_eq!(&bytes[0..2], b"BM", "not a BMP file");
+    let pixel_data_offset = u32::from_le_bytes(bytes[10..14].try_into().unwrap());
+
+    let dib_header_size = u32::from_le_bytes(bytes[14..18].try_into().unwrap());
+    assert!(dib_header_size >= 40, "only BITMAPINFOHEADER (or newer) BMPs are supported");
+
+    let width = i32::from_le_bytes(bytes[18..22].try_into().unwrap());
+    let height_raw = i32::from_le_bytes(bytes[22..26].try_into().unwrap());
+    let bits_per_pixel = u16::from_le_bytes(bytes[28..30].try_into().unwrap());
+
+    let width = u32!(width);
+    let top_down = height_raw < 0;
+    let height = u32!(height_raw.abs());
+
+    // an optional palette sits between the DIB header and the pixel data, used by
+    // paletted (<=8bpp) BMPs; each entry is stored as BGRA
+    let palette_offset = 14 + usize!(dib_header_size);
+    let palette: Vec<[u8; 3]> = if bits_per_pixel <= 8 {
+        let palette_bytes = &bytes[palette_offset..usize!(pixel_data_offset)];
+        palette_bytes.chunks_exact(4)
+            .map(|entry| [entry[2], entry[1], entry[0]])
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let row_stride = usize!((width * u32!(bits_per_pixel)).div_ceil(32) * 4);
+    let mut pixels = vec![[0u8; 3]; usize!(width) * usize!(height)];
+
+    for file_row in 0..height {
+        let row_start = usize!(pixel_data_offset) + usize!(file_row) * row_stride;
+        let row_bytes = &bytes[row_start..row_start + row_stride];
+
+        // BMP rows are bottom-up unless the header height is negative
+        let dest_row = if top_down { file_row } else { height - 1 - file_row };
+
+        for x in 0..width {
+            let rgb = match bits_per_pixel {
+                8 => palette[usize!(row_bytes[usize!(x)])],
+                24 => {
+                    let o = usize!(x) * 3;
+                    [row_bytes[o + 2], row_bytes[o + 1], row_bytes[o]]
+                },
+                32 => {
+                    let o = usize!(x) * 4;
+                    [row_bytes[o + 2], row_bytes[o + 1], row_bytes[o]]
+                },
+                other => panic!("unsupported BMP bit depth {}", other),
+            };
+            pixels[usize!(dest_row * width + x)] = rgb;
+        }
+    }
+
+    (width, height, pixels)
+}
+
+// maps a source RGB pixel onto the default two-entry palette (0 = black, 1 = white)
+#[inline]
+fn pixel_index_from_rgb(rgb: [u8; 3], low_threshold: u8, high_threshold: u8) -> Option<u8> {
+    let luminance = ((rgb[0] as u32 + rgb[1] as u32 + rgb[2] as u32) / 3) as u8;
+    if luminance >= high_threshold {
+        Some(1)
+    } else if luminance <= low_threshold {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+
+fn run_view_binary(opts: &ViewBinaryOpts) {
+    let buffer = std::fs::read(&opts.filename)
+        .expect("failed to read binary file");
+    let row_width = opts.row_width.unwrap_or_else(|| sniff_default_row_width(&buffer));
+    let mut state = BinaryViewState::new(buffer, row_width, opts.bit_expanded);
+
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+
+    let window = video_subsystem.window("pixelfield - binary view", 800, 600)
+        .position_centered()
+        .build()
+        .unwrap();
+
+    let mut canvas = window.into_canvas().build().unwrap();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    'running: loop {
+        render_binary(&mut canvas, &state);
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => break 'running,
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    match keycode {
+                        Keycode::Escape => break 'running,
+                        Keycode::S => {
+                            // write the (possibly patched) buffer back out
+                            std::fs::write(&opts.filename, &state.buffer)
+                                .expect("failed to write binary file");
+                        },
+                        Keycode::Space => toggle_bit_at_cursor(&mut state),
+                        Keycode::Left if state.x > 0 => state.x -= 1,
+                        Keycode::Right if state.x < state.columns() - 1 => state.x += 1,
+                        Keycode::Up if state.y > 0 => state.y -= 1,
+                        Keycode::Down if state.rows() > 0 && state.y < state.rows() - 1 => state.y += 1,
+                        Keycode::Home => {
+                            state.x = 0;
+                            state.y = 0;
+                        },
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        canvas.present();
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+    }
+}
+
+
+// magic tag identifying the compact bit-packed field format (as opposed to the
+// legacy pretty-printed JSON one)
+const BINARY_MAGIC: &[u8; 4] = b"PXFB";
+const BINARY_VERSION: u8 = 1;
+
+// a file is only written in the binary format if its extension asks for it; JSON
+// remains the default so existing tooling around it keeps working
+fn path_wants_binary_format(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("pxb"))
+        .unwrap_or(false)
+}
+
+// the palette's colors plus the "unset" value need `palette_len + 1` distinct
+// codes, so a row is packed at just enough bits per pixel to cover that
+fn bits_per_pixel_for_palette(palette_len: usize) -> u8 {
+    let code_count = palette_len + 1;
+    let mut bits = 0u8;
+    while (1usize << bits) < code_count {
+        bits += 1;
+    }
+    bits.max(1)
+}
+
+// packs `value` (which must fit in `bits_per_pixel` bits) into the pixel_index-th
+// slot of `row`, most-significant-bit first
+fn write_packed_pixel(row: &mut [u8], pixel_index: u32, bits_per_pixel: u8, value: u32) {
+    let bit_offset = usize!(pixel_index) * usize!(bits_per_pixel);
+    for bit in 0..bits_per_pixel {
+        let value_bit = (value >> (bits_per_pixel - 1 - bit)) & 1;
+        let absolute_bit = bit_offset + usize!(bit);
+        let byte_index = absolute_bit / 8;
+        let bit_in_byte = 7 - (absolute_bit % 8);
+        if value_bit == 1 {
+            row[byte_index] |= 1 << bit_in_byte;
+        }
+    }
+}
+
+fn read_packed_pixel(row: &[u8], pixel_index: u32, bits_per_pixel: u8) -> u32 {
+    let bit_offset = usize!(pixel_index) * usize!(bits_per_pixel);
+    let mut value = 0u32;
+    for bit in 0..bits_per_pixel {
+        let absolute_bit = bit_offset + usize!(bit);
+        let byte_index = absolute_bit / 8;
+        let bit_in_byte = 7 - (absolute_bit % 8);
+        let value_bit = (row[byte_index] >> bit_in_byte) & 1;
+        value = (value << 1) | u32::from(value_bit);
+    }
+    value
+}
+
+fn blend_mode_to_byte(mode: BlendMode) -> u8 {
+    match mode {
+        BlendMode::Normal => 0,
+        BlendMode::Multiply => 1,
+        BlendMode::Screen => 2,
+        BlendMode::Xor => 3,
+    }
+}
+
+fn blend_mode_from_byte(byte: u8) -> BlendMode {
+    match byte {
+        0 => BlendMode::Normal,
+        1 => BlendMode::Multiply,
+        2 => BlendMode::Screen,
+        3 => BlendMode::Xor,
+        other => panic!("unsupported blend mode byte {}", other),
+    }
+}
+
+// header layout: magic(4) | version(1) | width(4) | height(4) | bits_per_pixel(1)
+// | palette_len(1) | palette(palette_len*3) | stride(4) | layer_count(1) |
+// active_layer(1) | per layer: blend_mode(1) then its packed pixel rows
+fn encode_document_binary(document: &Document) -> Vec<u8> {
+    assert!(document.palette.len() <= 255, "binary format cannot store more than 255 palette entries (document has {})", document.palette.len());
+    assert!(document.layers.len() <= 255, "binary format cannot store more than 255 layers (document has {})", document.layers.len());
+
+    let width = document.width();
+    let height = document.height();
+    let bits_per_pixel = bits_per_pixel_for_palette(document.palette.len());
+    let stride = usize!((width * u32!(bits_per_pixel)).div_ceil(8));
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(BINARY_MAGIC);
+    bytes.push(BINARY_VERSION);
+    bytes.extend_from_slice(&width.to_le_bytes());
+    bytes.extend_from_slice(&height.to_le_bytes());
+    bytes.push(bits_per_pixel);
+    bytes.push(u8!(document.palette.len()));
+    for color in &document.palette {
+        bytes.extend_from_slice(&[color.r, color.g, color.b]);
+    }
+    bytes.extend_from_slice(&u32!(stride).to_le_bytes());
+    bytes.push(u8!(document.layers.len()));
+    bytes.push(u8!(document.active_layer));
+
+    for layer in &document.layers {
+        bytes.push(blend_mode_to_byte(layer.blend_mode));
+        for y in 0..height {
+            let row_start = bytes.len();
+            bytes.resize(row_start + stride, 0x00);
+            for x in 0..width {
+                // 0 means "unset"; a palette index is offset by one to make room for it
+                let code = match layer.image.pixels[usize!(y * width + x)] {
+                    None => 0,
+                    Some(index) => u32::from(index) + 1,
+                };
+                write_packed_pixel(&mut bytes[row_start..], x, bits_per_pixel, code);
+            }
+        }
+    }
+
+    bytes
+}
+
+// bails out with a descriptive panic instead of a bare slice-index-out-of-range
+// when a file is truncated (e.g. from a process killed mid-save)
+fn require_len(bytes: &[u8], end: usize, what: &str) {
+    assert!(bytes.len() >= end, "truncated pixelfield binary file: not enough data for {} (need {} bytes, have {})", what, end, bytes.len());
+}
+
+fn decode_document_binary(bytes: &[u8]) -> Document {
+    require_len(bytes, 5, "magic and version");
+    assert_eq!(&bytes[0..4], BINARY_MAGIC, "not a pixelfield binary file");
+    let version = bytes[4];
+    assert_eq!(version, BINARY_VERSION, "unsupported pixelfield binary version {}", version);
+
+    require_len(bytes, 15, "width, height, bits-per-pixel and palette length");
+    let width = u32::from_le_bytes(bytes[5..9].try_into().unwrap());
+    let height = u32::from_le_bytes(bytes[9..13].try_into().unwrap());
+    let bits_per_pixel = bytes[13];
+    let palette_len = usize!(bytes[14]);
+
+    let palette_start = 15;
+    require_len(bytes, palette_start + palette_len * 3, "palette");
+    let mut palette = Vec::with_capacity(palette_len);
+    for i in 0..palette_len {
+        let o = palette_start + i * 3;
+        palette.push(PaletteColor::new(bytes[o], bytes[o + 1], bytes[o + 2]));
+    }
+
+    let stride_start = palette_start + palette_len * 3;
+    require_len(bytes, stride_start + 6, "stride, layer count and active layer");
+    let stride = usize!(u32::from_le_bytes(bytes[stride_start..stride_start + 4].try_into().unwrap()));
+    let layer_count = usize!(bytes[stride_start + 4]);
+    let active_layer = usize!(bytes[stride_start + 5]);
+    assert!(layer_count >= 1, "corrupt pixelfield binary file: layer count is 0");
+    assert!(active_layer < layer_count, "corrupt pixelfield binary file: active layer {} is out of range for {} layer(s)", active_layer, layer_count);
+
+    let mut offset = stride_start + 6;
+    let mut layers = Vec::with_capacity(layer_count);
+    for _ in 0..layer_count {
+        require_len(bytes, offset + 1, "a layer's blend mode");
+        let blend_mode = blend_mode_from_byte(bytes[offset]);
+        offset += 1;
+
+        let mut pixels = Vec::with_capacity(usize!(width) * usize!(height));
+        for y in 0..height {
+            let row_start = offset + usize!(y) * stride;
+            require_len(bytes, row_start + stride, "a layer's pixel row");
+            let row = &bytes[row_start..row_start + stride];
+            for x in 0..width {
+                let code = read_packed_pixel(row, x, bits_per_pixel);
+                pixels.push(if code == 0 { None } else { Some(u8!(code - 1)) });
+            }
+        }
+        offset += usize!(height) * stride;
+
+        layers.push(Layer { image: Image { width, height, pixels }, blend_mode });
+    }
+
+    Document { palette, active_layer, layers }
+}
+
+// writes in the compact binary format if the extension asks for it, JSON otherwise
+fn save_document(document: &Document, path: &Path) {
+    if path_wants_binary_format(path) {
+        std::fs::write(path, encode_document_binary(document))
+            .expect("failed to write binary field file");
+    } else {
+        let f = File::create(path)
+            .expect("failed to create image file");
+        serde_json::to_writer_pretty(f, document)
+            .expect("failed to serialize document");
+    }
+}
+
+// detects the format from the file's leading bytes, regardless of extension, so
+// binary files keep working if renamed and old JSON files keep loading
+fn load_document(path: &Path) -> Document {
+    let bytes = std::fs::read(path)
+        .expect("failed to read image file");
+    if bytes.starts_with(BINARY_MAGIC) {
+        decode_document_binary(&bytes)
+    } else {
+        serde_json::from_slice(&bytes)
+            .expect("failed to deserialize document")
+    }
+}
+
+
 fn main() {
     let args = Mode::parse();
-    let (image_filename, image) = match &args {
+    let (document_filename, document) = match &args {
         Mode::Create(create_opts) => {
-            let image = Image::new(create_opts.width, create_opts.height);
-            let f = File::create(&create_opts.filename)
-                .expect("failed to create image file");
-            serde_json::to_writer_pretty(f, &image)
-                .expect("failed to serialize initial image");
-            (&create_opts.filename, image)
+            let document = Document::new(create_opts.width, create_opts.height);
+            save_document(&document, &create_opts.filename);
+            (&create_opts.filename, document)
         },
         Mode::Open(open_opts) => {
-            let f = File::open(&open_opts.filename)
-                .expect("failed to open image file");
-            let image = serde_json::from_reader(f)
-                .expect("failed to deserialize image file");
-            (&open_opts.filename, image)
+            let document = load_document(&open_opts.filename);
+            (&open_opts.filename, document)
         },
         Mode::ToPng(to_png_opts) => {
-            let image: Image = {
-                let f = File::open(&to_png_opts.field_filename)
-                    .expect("failed to open field image file");
-                serde_json::from_reader(f)
-                    .expect("failed to deserialize field image file")
-            };
+            let document = load_document(&to_png_opts.field_filename);
+            let composite = composite_document(&document);
 
             {
                 let f = File::create(&to_png_opts.png_filename)
                     .expect("failed to open PNG file");
-                let mut png_image = png::Encoder::new(f, image.width, image.height);
-                png_image.set_color(png::ColorType::Grayscale);
+                let mut png_image = png::Encoder::new(f, composite.width, composite.height);
+                png_image.set_color(png::ColorType::Indexed);
                 png_image.set_depth(png::BitDepth::Eight);
+
+                // the "unset" value has no palette entry of its own; give it one extra
+                // slot at the end of the palette, using the neutral grey the editor shows
+                // unset pixels in; with its default thresholds, FromImage reads it back as unset
+                let none_index = u8!(document.palette.len());
+                let mut palette_bytes = Vec::with_capacity((document.palette.len() + 1) * 3);
+                for color in &document.palette {
+                    palette_bytes.extend_from_slice(&[color.r, color.g, color.b]);
+                }
+                palette_bytes.extend_from_slice(&[0x7F, 0x7F, 0x7F]);
+                png_image.set_palette(palette_bytes);
+
                 let mut png_writer = png_image.write_header()
                     .expect("failed to write PNG header");
 
-                let mut pixel_data = Vec::with_capacity(image.pixels.len());
-                for pixel in &image.pixels {
-                    match pixel {
-                        Some(true) => pixel_data.push(0xFF),
-                        Some(false) => pixel_data.push(0x00),
-                        None => pixel_data.push(0x7F),
-                    }
-                }
+                let pixel_data: Vec<u8> = composite.pixels.iter()
+                    .map(|pixel| pixel.unwrap_or(none_index))
+                    .collect();
                 png_writer.write_image_data(&pixel_data)
                     .expect("failed to write pixel data");
             }
             return;
-        }
+        },
+        Mode::FromImage(from_image_opts) => {
+            let (width, height, rgb_pixels) = decode_image_rgb(&from_image_opts.image_filename);
+
+            let pixels = rgb_pixels.into_iter()
+                .map(|rgb| pixel_index_from_rgb(rgb, from_image_opts.low_threshold, from_image_opts.high_threshold))
+                .collect();
+            let document = Document {
+                palette: DEFAULT_PALETTE.to_vec(),
+                active_layer: 0,
+                layers: vec![Layer::new(Image { width, height, pixels })],
+            };
+            save_document(&document, &from_image_opts.field_filename);
+            return;
+        },
+        Mode::ViewBinary(view_binary_opts) => {
+            run_view_binary(view_binary_opts);
+            return;
+        },
     };
 
-    let mut ui_state = UiState::new(image);
+    let mut ui_state = UiState::new(document);
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
@@ -305,13 +1098,14 @@ fn main() {
                         match keycode {
                             Keycode::Escape => break 'running,
                             Keycode::R => ui_state.going_right = !ui_state.going_right,
-                            Keycode::X => ui_state.setting_mode = !ui_state.setting_mode,
+                            Keycode::X => {
+                                // cycle the active palette entry
+                                let palette_len = u8!(ui_state.document.palette.len());
+                                ui_state.setting_mode = (ui_state.setting_mode + 1) % palette_len;
+                            },
                             Keycode::S => {
-                                // save the current image
-                                let f = File::create(image_filename)
-                                    .expect("failed to create image file");
-                                serde_json::to_writer_pretty(f, &ui_state.image)
-                                    .expect("failed to serialize initial image");
+                                // save the current document
+                                save_document(&ui_state.document, document_filename);
                             },
                             Keycode::Left => {
                                 if ui_state.x > 0 {
@@ -319,7 +1113,7 @@ fn main() {
                                 }
                             },
                             Keycode::Right => {
-                                if ui_state.x < ui_state.image.width - 1 {
+                                if ui_state.x < ui_state.document.width() - 1 {
                                     ui_state.x += 1;
                                 }
                             },
@@ -329,7 +1123,7 @@ fn main() {
                                 }
                             },
                             Keycode::Down => {
-                                if ui_state.y < ui_state.image.height - 1 {
+                                if ui_state.y < ui_state.document.height() - 1 {
                                     ui_state.y += 1;
                                 }
                             },
@@ -337,48 +1131,82 @@ fn main() {
                                 ui_state.x = 0;
                                 ui_state.y = 0;
                             },
-                            Keycode::T => {
-                                // set current pixel to true
-                                let image_index = usize!(ui_state.y * ui_state.image.width + ui_state.x);
-                                ui_state.image.pixels[image_index] = Some(true);
-                            },
-                            Keycode::F => {
-                                // set current pixel to false
-                                let image_index = usize!(ui_state.y * ui_state.image.width + ui_state.x);
-                                ui_state.image.pixels[image_index] = Some(false);
+                            Keycode::Space => {
+                                // stamp the current pixel with the active palette entry and advance
+                                let (x, y, setting_mode) = (ui_state.x, ui_state.y, ui_state.setting_mode);
+                                let width = ui_state.document.width();
+                                let image = ui_state.active_image_mut();
+                                let image_index = usize!(y * width + x);
+                                image.pixels[image_index] = Some(setting_mode);
+                                advance_cursor(&mut ui_state);
                             },
                             Keycode::Backspace|Keycode::Delete => {
-                                // set current pixel to null
-                                let image_index = usize!(ui_state.y * ui_state.image.width + ui_state.x);
-                                ui_state.image.pixels[image_index] = None;
+                                // set current pixel to null on the active layer
+                                let (x, y) = (ui_state.x, ui_state.y);
+                                let width = ui_state.document.width();
+                                let image = ui_state.active_image_mut();
+                                let image_index = usize!(y * width + x);
+                                image.pixels[image_index] = None;
+                            },
+                            Keycode::M => {
+                                // mark the cursor as the anchor for the next rectangle fill
+                                ui_state.anchor = Some((ui_state.x, ui_state.y));
+                            },
+                            Keycode::B => {
+                                // fill the box between the anchor and the cursor on the active layer
+                                if let Some((anchor_x, anchor_y)) = ui_state.anchor {
+                                    let (x, y, setting_mode) = (ui_state.x, ui_state.y, ui_state.setting_mode);
+                                    rect_fill(ui_state.active_image_mut(), anchor_x, anchor_y, x, y, Some(setting_mode));
+                                }
+                            },
+                            Keycode::G => {
+                                // flood-fill the region sharing the cursor's value on the active layer
+                                let (x, y, setting_mode) = (ui_state.x, ui_state.y, ui_state.setting_mode);
+                                flood_fill(ui_state.active_image_mut(), x, y, Some(setting_mode));
+                            },
+                            Keycode::N => {
+                                // add a fresh, blank layer on top of the stack and make it active;
+                                // capped at 255 because the binary format stores the layer count
+                                // and the active layer index in a single byte each
+                                if ui_state.document.layers.len() < 255 {
+                                    let (width, height) = (ui_state.document.width(), ui_state.document.height());
+                                    ui_state.document.layers.push(Layer::new(Image::new(width, height)));
+                                    ui_state.document.active_layer = ui_state.document.layers.len() - 1;
+                                }
+                            },
+                            Keycode::D => {
+                                // delete the active layer, as long as one will remain
+                                if ui_state.document.layers.len() > 1 {
+                                    ui_state.document.layers.remove(ui_state.document.active_layer);
+                                    if ui_state.document.active_layer >= ui_state.document.layers.len() {
+                                        ui_state.document.active_layer = ui_state.document.layers.len() - 1;
+                                    }
+                                }
+                            },
+                            Keycode::LeftBracket => {
+                                // switch to the layer below the active one
+                                if ui_state.document.active_layer > 0 {
+                                    ui_state.document.active_layer -= 1;
+                                }
+                            },
+                            Keycode::RightBracket => {
+                                // switch to the layer above the active one
+                                if ui_state.document.active_layer < ui_state.document.layers.len() - 1 {
+                                    ui_state.document.active_layer += 1;
+                                }
+                            },
+                            Keycode::C => {
+                                // cycle the active layer's blend mode
+                                let active_layer = ui_state.document.active_layer;
+                                let layer = &mut ui_state.document.layers[active_layer];
+                                layer.blend_mode = layer.blend_mode.next();
                             },
                             other => {
                                 if let Some(digit) = keycode_to_digit(other) {
-                                    for _ in 0..digit {
-                                        let image_index = usize!(ui_state.y * ui_state.image.width + ui_state.x);
-                                        ui_state.image.pixels[image_index] = Some(ui_state.setting_mode);
-                                        
-                                        if ui_state.going_right {
-                                            if ui_state.x < ui_state.image.width - 1 {
-                                                ui_state.x += 1;
-                                            } else {
-                                                ui_state.going_right = false;
-                                                if ui_state.y < ui_state.image.height - 1 {
-                                                    ui_state.y += 1;
-                                                }
-                                            }
-                                        } else {
-                                            if ui_state.x > 0 {
-                                                ui_state.x -= 1;
-                                            } else {
-                                                ui_state.going_right = true;
-                                                if ui_state.y < ui_state.image.height - 1 {
-                                                    ui_state.y += 1;
-                                                }
-                                            }
-                                        }
+                                    // select the palette entry at this index, if there is one
+                                    if usize!(digit) < ui_state.document.palette.len() {
+                                        ui_state.setting_mode = u8!(digit);
                                     }
-                                    ui_state.setting_mode = !ui_state.setting_mode;
                                 }
                             },
                         }